@@ -0,0 +1,152 @@
+/// Evaluation harness: sweeps several predefined tasksets at varying
+/// utilization levels under both the NN policy and the EDF/round-robin
+/// baseline (the round-robin quantum applies to every policy — see
+/// `Scheduler::tick_once`), for a full hyperperiod each, and prints a
+/// comparison table over semihosting.
+use crate::policy::{self, EdfPolicy, SchedulingPolicy};
+use crate::scheduler::Scheduler;
+use crate::task::{Task, NUM_TASKS};
+use cortex_m_semihosting::hprintln;
+
+/// A named, predefined taskset at a given utilization level.
+struct Taskset {
+    name: &'static str,
+    tasks: [Task; NUM_TASKS],
+}
+
+fn tasksets() -> [Taskset; 3] {
+    [
+        Taskset {
+            name: "under-loaded",
+            // Utilization ~= 0.43
+            tasks: [
+                Task::new(0, 10, 10, 1),
+                Task::new(1, 15, 15, 1),
+                Task::new(2, 20, 20, 2),
+                Task::new(3, 30, 30, 2),
+                Task::new(4, 50, 50, 3),
+                Task::new(5, 100, 100, 4),
+            ],
+        },
+        Taskset {
+            name: "critically-loaded",
+            // Same taskset as the Python training environment, utilization ~= 1.03
+            tasks: [
+                Task::new(0, 10, 10, 2),
+                Task::new(1, 15, 15, 3),
+                Task::new(2, 20, 20, 4),
+                Task::new(3, 30, 30, 5),
+                Task::new(4, 50, 50, 8),
+                Task::new(5, 100, 100, 10),
+            ],
+        },
+        Taskset {
+            name: "overloaded",
+            // Utilization ~= 1.32
+            tasks: [
+                Task::new(0, 10, 10, 3),
+                Task::new(1, 15, 15, 4),
+                Task::new(2, 20, 20, 5),
+                Task::new(3, 30, 30, 6),
+                Task::new(4, 50, 50, 9),
+                Task::new(5, 100, 100, 12),
+            ],
+        },
+    ]
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lcm(a: u32, b: u32) -> u32 {
+    a / gcd(a, b) * b
+}
+
+/// The hyperperiod of a taskset: the LCM of all task periods.
+fn hyperperiod(tasks: &[Task; NUM_TASKS]) -> u32 {
+    tasks.iter().fold(1, |acc, t| lcm(acc, t.period))
+}
+
+/// Aggregate results for one (taskset, policy) run, gathered after
+/// `Scheduler::run_silent` has executed a full hyperperiod.
+struct RunStats {
+    misses: u32,
+    completions: u32,
+    switches: u32,
+    releases: u32,
+    per_task_misses: [u32; NUM_TASKS],
+}
+
+impl RunStats {
+    fn from_scheduler<P: SchedulingPolicy>(sched: &Scheduler<P>) -> Self {
+        let mut per_task_misses = [0u32; NUM_TASKS];
+        for (i, t) in sched.tasks.iter().enumerate() {
+            per_task_misses[i] = t.deadline_misses;
+        }
+        Self {
+            misses: sched.total_misses,
+            completions: sched.total_completions,
+            switches: sched.context_switches,
+            releases: sched.total_releases,
+            per_task_misses,
+        }
+    }
+
+    /// Deadline misses per 1000 released jobs, so miss rates are comparable
+    /// across tasksets with different hyperperiods/release counts.
+    fn miss_rate_permille(&self) -> u32 {
+        if self.releases == 0 {
+            0
+        } else {
+            self.misses * 1000 / self.releases
+        }
+    }
+}
+
+fn print_row(label: &str, stats: &RunStats) {
+    let _ = hprintln!(
+        "  {:<8} misses={:<4} completions={:<4} switches={:<4} releases={:<4} miss_permille={}",
+        label,
+        stats.misses,
+        stats.completions,
+        stats.switches,
+        stats.releases,
+        stats.miss_rate_permille()
+    );
+    let _ = hprintln!("           per-task misses: {:?}", stats.per_task_misses);
+}
+
+/// Run the full evaluation sweep and print a comparison table over
+/// semihosting: for each taskset, the NN policy vs. the EDF/round-robin
+/// baseline, over one full hyperperiod each.
+///
+/// "NN" here is `policy::demo_nn_policy()`: a hand-derived, not trained,
+/// blob that reproduces EDF (see `policy::demo_blob`), loaded through the
+/// same `load_weights` path a real trained blob would use. It's a stand-in
+/// to exercise that path end-to-end -- it is not yet a learned policy, so
+/// this table doesn't quantify any learned benefit over EDF.
+pub fn run_eval() {
+    let _ = hprintln!("\n=== Evaluation: NN policy vs EDF/round-robin baseline ===");
+    let _ = hprintln!("(NN is a hand-derived EDF-equivalent stand-in, not a trained net)");
+
+    for set in tasksets().iter() {
+        let ticks = hyperperiod(&set.tasks);
+
+        let mut nn_sched = Scheduler::new(set.tasks, policy::demo_nn_policy());
+        nn_sched.run_silent(ticks);
+        let nn_stats = RunStats::from_scheduler(&nn_sched);
+
+        let mut edf_sched = Scheduler::new(set.tasks, EdfPolicy::default());
+        edf_sched.run_silent(ticks);
+        let edf_stats = RunStats::from_scheduler(&edf_sched);
+
+        let _ = hprintln!("\n-- {} (hyperperiod={} ticks) --", set.name, ticks);
+        print_row("NN", &nn_stats);
+        print_row("EDF+RR", &edf_stats);
+    }
+}
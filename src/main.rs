@@ -2,12 +2,16 @@
 //!
 //! Runs on ARM Cortex-M4 (STM32F411) under QEMU. Defines the same 6-task
 //! periodic taskset used in Python training, then runs the scheduler for
-//! one hyperperiod (300 ticks). Output goes via semihosting to the QEMU console.
+//! one hyperperiod (300 ticks), followed by the `eval` harness's sweep
+//! comparing the NN policy against the EDF/round-robin baseline across
+//! several tasksets. Output goes via semihosting to the QEMU console.
 
 #![no_std]
 #![no_main]
 
+mod eval;
 mod policy;
+mod resource;
 mod scheduler;
 mod task;
 
@@ -27,21 +31,39 @@ fn main() -> ! {
     // Same taskset as Python training: (period, deadline, wcet)
     // Total utilization ≈ 1.03 — intentionally overloaded to show
     // how the RL policy minimizes deadline misses under pressure.
+    //
+    // Tasks 0 and 4 also share resource 0: task 4 (low urgency) grabs it
+    // with 4 ticks of work left and holds it for 2 ticks, while task 0
+    // (highest urgency) requests it right before finishing, exercising the
+    // priority-inheritance path in `resource::ResourceManager`.
+    //
+    // Task 5's CBS budget is tightened below its wcet so it reliably
+    // overruns its server budget partway through each job, exercising the
+    // CBS suspension path in `Task::cbs_suspend` (with the default Q ==
+    // wcet, a job always finishes on the exact tick its budget hits zero,
+    // so it can never be observed overrunning).
     let tasks = [
-        Task::new(0, 10, 10, 2),
+        Task::new(0, 10, 10, 2).with_cs(0, 1, 1),
         Task::new(1, 15, 15, 3),
         Task::new(2, 20, 20, 4),
         Task::new(3, 30, 30, 5),
-        Task::new(4, 50, 50, 8),
-        Task::new(5, 100, 100, 10),
+        Task::new(4, 50, 50, 8).with_cs(0, 4, 2),
+        Task::new(5, 100, 100, 10).with_budget(6),
     ];
 
-    let mut sched = Scheduler::new(tasks);
+    // `demo_nn_policy` loads a hand-derived (not trained) EDF-equivalent
+    // blob via `policy::load_weights`, standing in for a real trained
+    // network until one exists -- with the all-zero placeholder weights,
+    // the net always argmaxes to task 0 regardless of state.
+    let mut sched = Scheduler::new(tasks, policy::demo_nn_policy());
 
     // Run for one hyperperiod: LCM(10,15,20,30,50,100) = 300 ticks
     sched.run(300);
 
     let _ = hprintln!("\nScheduler finished. Halting.");
+
+    eval::run_eval();
+
     debug::exit(debug::EXIT_SUCCESS);
 
     loop {}
@@ -1,31 +1,54 @@
-/// Fixed-point neural network inference for the scheduling policy.
+/// Scheduling policies for the RTOS scheduler.
 ///
-/// Uses Q10 format: multiply float by 1024 and round to i32.
-/// This avoids floating-point entirely, which is important on embedded
-/// targets where FPU use can introduce non-deterministic timing and
-/// where we want the scheduler decision to be fast and predictable.
-///
-/// Network architecture: 24 inputs -> 32 (ReLU) -> 32 (ReLU) -> 7 outputs.
-/// The argmax of the output selects which task to run (0-5) or idle (6).
+/// A `SchedulingPolicy` picks which task (if any) should run on the next
+/// tick. `Scheduler` is generic over the policy so the same tick loop and
+/// metrics (misses/completions/switches) can be reused to compare the
+/// learned policy against deterministic baselines.
+use crate::task::{Task, TaskState, NUM_TASKS};
+
+/// Selects the next task to run each tick.
+pub trait SchedulingPolicy {
+    /// Choose a task index to run this tick, or `None` to idle.
+    fn select(&mut self, tasks: &[Task; NUM_TASKS], tick: u32) -> Option<usize>;
+}
+
+// ── NN policy ─────────────────────────────────────────────────────────
+//
+// Fixed-point neural network inference for the scheduling policy.
+//
+// Uses Q10 format: multiply float by 1024 and round to i32.
+// This avoids floating-point entirely, which is important on embedded
+// targets where FPU use can introduce non-deterministic timing and
+// where we want the scheduler decision to be fast and predictable.
+//
+// Network architecture: 24 inputs -> 32 (ReLU) -> 32 (ReLU) -> 7 outputs.
+// The argmax of the output selects which task to run (0-5) or idle (6).
 
 const SCALE: i32 = 1024;
 const IN: usize = 24;
 const H: usize = 32;
 const OUT: usize = 7;
+const Q10: i32 = 1024;
+const STATE_SIZE: usize = NUM_TASKS * 4;
 
-// ── Placeholder weights ──────────────────────────────────────────────
-// Replace these with values from policy_weights.json (weights_q10 fields)
-// after training. Each weight matrix is stored as [output_neurons][input_neurons]
-// for cache-friendly row iteration during inference.
-
-static W1: [[i32; IN]; H] = [[0; IN]; H];
-static B1: [i32; H] = [0; H];
+/// Magic header identifying a packed Q10 weight blob (see `load_weights`).
+const MAGIC: [u8; 4] = *b"Q10W";
 
-static W2: [[i32; H]; H] = [[0; H]; H];
-static B2: [i32; H] = [0; H];
+/// Header (magic + 3 dimensions) plus the size of every layer's weights.
+const HEADER_LEN: usize = 16;
+const BLOB_I32_COUNT: usize = H * IN + H + H * H + H + OUT * H + OUT;
+const BLOB_LEN: usize = HEADER_LEN + 4 * BLOB_I32_COUNT;
 
-static W3: [[i32; H]; OUT] = [[0; H]; OUT];
-static B3: [i32; OUT] = [0; OUT];
+/// Errors returned by `load_weights` when a blob can't be parsed into a `Policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightError {
+    /// The blob doesn't start with the expected `Q10W` magic.
+    BadMagic,
+    /// The blob's declared `IN/H/OUT` dimensions don't match this build.
+    DimensionMismatch,
+    /// The blob is shorter than its header declares it should be.
+    Truncated,
+}
 
 #[inline]
 fn relu(x: i32) -> i32 {
@@ -36,47 +59,331 @@ fn relu(x: i32) -> i32 {
     }
 }
 
-/// Run the policy network on a Q10-encoded state vector.
-/// Returns the action index (0..6).
+/// A set of Q10 fixed-point NN weights: 24 inputs -> 32 (ReLU) -> 32 (ReLU)
+/// -> 7 outputs. The argmax of the output selects which task to run (0-5)
+/// or idle (6).
 ///
-/// Computation per layer: out[j] = ReLU( sum_i(W[j][i] * input[i]) / SCALE + B[j] )
-/// The division by SCALE after multiply-accumulate keeps values in Q10 range.
-/// Final layer has no ReLU — we just take the argmax.
-pub fn infer(state: &[i32; IN]) -> usize {
-    // Layer 1: IN -> H with ReLU
-    let mut h1 = [0i32; H];
+/// Uses Q10 format: multiply float by 1024 and round to i32. This avoids
+/// floating-point entirely, which is important on embedded targets where
+/// FPU use can introduce non-deterministic timing and where we want the
+/// scheduler decision to be fast and predictable.
+pub struct Policy {
+    w1: [[i32; IN]; H],
+    b1: [i32; H],
+    w2: [[i32; H]; H],
+    b2: [i32; H],
+    w3: [[i32; H]; OUT],
+    b3: [i32; OUT],
+}
+
+impl Policy {
+    /// All-zero placeholder weights. Every tick ties at 0, so this always
+    /// selects task 0 — useful until a trained blob is loaded via `load_weights`.
+    pub const fn placeholder() -> Self {
+        Self {
+            w1: [[0; IN]; H],
+            b1: [0; H],
+            w2: [[0; H]; H],
+            b2: [0; H],
+            w3: [[0; H]; OUT],
+            b3: [0; OUT],
+        }
+    }
+
+    /// Run the policy network on a Q10-encoded state vector.
+    /// Returns the action index (0..OUT).
+    ///
+    /// Computation per layer: out[j] = ReLU( sum_i(W[j][i] * input[i]) / SCALE + B[j] )
+    /// The division by SCALE after multiply-accumulate keeps values in Q10 range.
+    /// Final layer has no ReLU — we just take the argmax.
+    pub fn infer(&self, state: &[i32; IN]) -> usize {
+        // Layer 1: IN -> H with ReLU
+        let mut h1 = [0i32; H];
+        for j in 0..H {
+            let mut acc: i32 = 0;
+            for i in 0..IN {
+                acc = acc.saturating_add(self.w1[j][i].saturating_mul(state[i]));
+            }
+            h1[j] = relu(acc / SCALE + self.b1[j]);
+        }
+
+        // Layer 2: H -> H with ReLU
+        let mut h2 = [0i32; H];
+        for j in 0..H {
+            let mut acc: i32 = 0;
+            for i in 0..H {
+                acc = acc.saturating_add(self.w2[j][i].saturating_mul(h1[i]));
+            }
+            h2[j] = relu(acc / SCALE + self.b2[j]);
+        }
+
+        // Output layer: H -> OUT (no activation, just argmax)
+        let mut best_idx: usize = 0;
+        let mut best_val: i32 = i32::MIN;
+        for j in 0..OUT {
+            let mut acc: i32 = 0;
+            for i in 0..H {
+                acc = acc.saturating_add(self.w3[j][i].saturating_mul(h2[i]));
+            }
+            let val = acc / SCALE + self.b3[j];
+            if val > best_val {
+                best_val = val;
+                best_idx = j;
+            }
+        }
+
+        best_idx
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::placeholder()
+    }
+}
+
+/// Parse a packed Q10 weight blob into a `Policy`, so a trained network can
+/// be flashed into a dedicated memory region (or chosen among several
+/// candidates at boot) without recompiling.
+///
+/// Blob layout (all integers little-endian):
+/// `magic: [u8; 4]="Q10W"`, `in_dim: u32`, `hidden_dim: u32`, `out_dim: u32`,
+/// followed by `w1`, `b1`, `w2`, `b2`, `w3`, `b3` as `i32` arrays in that order.
+pub fn load_weights(blob: &[u8]) -> Result<Policy, WeightError> {
+    if blob.len() < HEADER_LEN {
+        return Err(WeightError::Truncated);
+    }
+    if blob[0..4] != MAGIC {
+        return Err(WeightError::BadMagic);
+    }
+
+    let in_dim = read_u32(blob, 4) as usize;
+    let hidden_dim = read_u32(blob, 8) as usize;
+    let out_dim = read_u32(blob, 12) as usize;
+    if in_dim != IN || hidden_dim != H || out_dim != OUT {
+        return Err(WeightError::DimensionMismatch);
+    }
+    if blob.len() < BLOB_LEN {
+        return Err(WeightError::Truncated);
+    }
+
+    let mut cursor = HEADER_LEN;
+    let mut policy = Policy::placeholder();
+
+    for row in policy.w1.iter_mut() {
+        for v in row.iter_mut() {
+            *v = read_i32(blob, cursor);
+            cursor += 4;
+        }
+    }
+    for v in policy.b1.iter_mut() {
+        *v = read_i32(blob, cursor);
+        cursor += 4;
+    }
+    for row in policy.w2.iter_mut() {
+        for v in row.iter_mut() {
+            *v = read_i32(blob, cursor);
+            cursor += 4;
+        }
+    }
+    for v in policy.b2.iter_mut() {
+        *v = read_i32(blob, cursor);
+        cursor += 4;
+    }
+    for row in policy.w3.iter_mut() {
+        for v in row.iter_mut() {
+            *v = read_i32(blob, cursor);
+            cursor += 4;
+        }
+    }
+    for v in policy.b3.iter_mut() {
+        *v = read_i32(blob, cursor);
+        cursor += 4;
+    }
+
+    Ok(policy)
+}
+
+#[inline]
+fn read_u32(blob: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([blob[at], blob[at + 1], blob[at + 2], blob[at + 3]])
+}
+
+#[inline]
+fn read_i32(blob: &[u8], at: usize) -> i32 {
+    i32::from_le_bytes([blob[at], blob[at + 1], blob[at + 2], blob[at + 3]])
+}
+
+#[inline]
+fn write_i32(blob: &mut [u8; BLOB_LEN], at: &mut usize, v: i32) {
+    blob[*at..*at + 4].copy_from_slice(&v.to_le_bytes());
+    *at += 4;
+}
+
+/// A hand-derived (not trained) Q10 blob that reproduces EDF: each task's
+/// output neuron scores `-time_to_deadline`, gated hard by `is_ready` so a
+/// non-ready task can never outscore a ready one, and the idle output only
+/// wins when nothing is ready. The first two layers pass the state vector
+/// through unchanged (identity weights; every state feature is already
+/// non-negative, so ReLU is a no-op), so the output layer can read it
+/// directly. Stands in for a real trained blob -- see `load_weights` --
+/// until one exists, so `NnPolicy` has something other than the all-zero
+/// placeholder to load and run.
+pub fn demo_blob() -> [u8; BLOB_LEN] {
+    let mut blob = [0u8; BLOB_LEN];
+    blob[0..4].copy_from_slice(&MAGIC);
+    blob[4..8].copy_from_slice(&(IN as u32).to_le_bytes());
+    blob[8..12].copy_from_slice(&(H as u32).to_le_bytes());
+    blob[12..16].copy_from_slice(&(OUT as u32).to_le_bytes());
+
+    let mut at = HEADER_LEN;
+
+    // w1, b1: identity passthrough on the first STATE_SIZE (== IN) hidden
+    // units; the rest are unused.
     for j in 0..H {
-        let mut acc: i32 = 0;
         for i in 0..IN {
-            acc = acc.saturating_add(W1[j][i].saturating_mul(state[i]));
+            write_i32(&mut blob, &mut at, if j == i { Q10 } else { 0 });
         }
-        h1[j] = relu(acc / SCALE + B1[j]);
+    }
+    for _ in 0..H {
+        write_i32(&mut blob, &mut at, 0);
     }
 
-    // Layer 2: H -> H with ReLU
-    let mut h2 = [0i32; H];
+    // w2, b2: identity passthrough again, so h2 == state.
     for j in 0..H {
-        let mut acc: i32 = 0;
         for i in 0..H {
-            acc = acc.saturating_add(W2[j][i].saturating_mul(h1[i]));
+            write_i32(&mut blob, &mut at, if j == i && j < IN { Q10 } else { 0 });
         }
-        h2[j] = relu(acc / SCALE + B2[j]);
+    }
+    for _ in 0..H {
+        write_i32(&mut blob, &mut at, 0);
     }
 
-    // Output layer: H -> OUT (no activation, just argmax)
-    let mut best_idx: usize = 0;
-    let mut best_val: i32 = i32::MIN;
+    // w3, b3: out[j] = 4*Q10*is_ready_j - ttd_j - 2*Q10 for task outputs,
+    // so a ready task always scores in [Q10, 2*Q10] (lower ttd wins) and a
+    // non-ready task always scores -2*Q10. Idle is biased to -Q10, beating
+    // any non-ready task but losing to any ready one.
     for j in 0..OUT {
-        let mut acc: i32 = 0;
         for i in 0..H {
-            acc = acc.saturating_add(W3[j][i].saturating_mul(h2[i]));
+            let v = if j < NUM_TASKS && i == j * 4 {
+                -Q10
+            } else if j < NUM_TASKS && i == j * 4 + 3 {
+                4 * Q10
+            } else {
+                0
+            };
+            write_i32(&mut blob, &mut at, v);
         }
-        let val = acc / SCALE + B3[j];
-        if val > best_val {
-            best_val = val;
-            best_idx = j;
+    }
+    for j in 0..OUT {
+        write_i32(&mut blob, &mut at, if j < NUM_TASKS { -2 * Q10 } else { -Q10 });
+    }
+
+    blob
+}
+
+/// An `NnPolicy` loaded from `demo_blob` via `load_weights`. `demo_blob` is
+/// known-good, so parsing it can't fail.
+pub fn demo_nn_policy() -> NnPolicy {
+    NnPolicy::new(load_weights(&demo_blob()).unwrap_or_else(|_| Policy::placeholder()))
+}
+
+/// Wraps fixed-point NN inference (see `Policy`) as a `SchedulingPolicy`.
+#[derive(Default)]
+pub struct NnPolicy {
+    policy: Policy,
+}
+
+impl NnPolicy {
+    /// Run with an explicit (e.g. runtime-loaded) set of weights.
+    pub fn new(policy: Policy) -> Self {
+        Self { policy }
+    }
+
+    /// Build the Q10 fixed-point state vector matching the Python environment.
+    /// 4 features per task: time_to_deadline, time_since_scheduled, remaining/wcet, is_ready.
+    fn build_state(tasks: &[Task; NUM_TASKS], tick: u32) -> [i32; STATE_SIZE] {
+        let max_deadline = tasks.iter().map(|t| t.deadline).max().unwrap_or(1);
+
+        let mut state = [0i32; STATE_SIZE];
+        for (i, t) in tasks.iter().enumerate() {
+            let base = i * 4;
+            if t.state == TaskState::Ready {
+                // Time remaining until deadline, normalized. Uses the
+                // effective deadline so a task that has inherited a more
+                // urgent waiter's deadline is seen as more urgent too.
+                let deadline = t.effective_deadline();
+                let ttd = if deadline > tick {
+                    (deadline - tick) as i32 * Q10 / max_deadline as i32
+                } else {
+                    0
+                };
+                state[base] = clamp(ttd, 0, Q10);
+
+                // Time since last scheduled, normalized the same way as
+                // time-to-deadline. A task that has never run is treated as
+                // maximally stale (clamped to Q10), matching the Python feature.
+                let since = match t.last_scheduled {
+                    Some(last) => tick.saturating_sub(last) as i32 * Q10 / max_deadline as i32,
+                    None => Q10,
+                };
+                state[base + 1] = clamp(since, 0, Q10);
+
+                // Remaining execution / WCET
+                state[base + 2] = t.remaining as i32 * Q10 / t.wcet as i32;
+
+                // Is ready
+                state[base + 3] = Q10;
+            }
         }
+        state
     }
+}
 
-    best_idx
+impl SchedulingPolicy for NnPolicy {
+    fn select(&mut self, tasks: &[Task; NUM_TASKS], tick: u32) -> Option<usize> {
+        let state = Self::build_state(tasks, tick);
+        let action = self.policy.infer(&state);
+        if action < NUM_TASKS {
+            Some(action)
+        } else {
+            None
+        }
+    }
+}
+
+// ── EDF baseline ──────────────────────────────────────────────────────
+
+/// Earliest-Deadline-First: picks the `Ready` task with the smallest
+/// effective deadline (see `Task::effective_deadline`), breaking ties by
+/// task id. A deterministic, analyzable baseline to compare the learned
+/// policy against.
+#[derive(Default)]
+pub struct EdfPolicy;
+
+impl SchedulingPolicy for EdfPolicy {
+    fn select(&mut self, tasks: &[Task; NUM_TASKS], _tick: u32) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for (i, t) in tasks.iter().enumerate() {
+            if t.state != TaskState::Ready {
+                continue;
+            }
+            best = match best {
+                Some(b) if tasks[b].effective_deadline() <= t.effective_deadline() => Some(b),
+                _ => Some(i),
+            };
+        }
+        best
+    }
+}
+
+#[inline]
+fn clamp(val: i32, min: i32, max: i32) -> i32 {
+    if val < min {
+        min
+    } else if val > max {
+        max
+    } else {
+        val
+    }
 }
@@ -0,0 +1,125 @@
+/// Mutex subsystem with priority inheritance.
+///
+/// A fixed set of mutexes guards access to shared resources. Each task's
+/// critical-section schedule (`Task::cs`) deterministically drives
+/// acquire/release so behavior is reproducible under QEMU. When the running
+/// task tries to acquire a held mutex it is moved to `TaskState::Blocked`
+/// and the dependency is recorded; releasing wakes the highest-priority
+/// (smallest deadline) waiter. To bound priority inversion, a holder that a
+/// more-urgent task is blocked on temporarily inherits the waiter's
+/// deadline for scheduling purposes.
+use crate::task::{Task, TaskState, NUM_TASKS};
+
+pub const NUM_RESOURCES: usize = 2;
+
+/// A single mutex: tracks which task (if any) currently holds it.
+#[derive(Clone, Copy, Default)]
+struct Mutex {
+    holder: Option<usize>,
+}
+
+pub struct ResourceManager {
+    mutexes: [Mutex; NUM_RESOURCES],
+}
+
+impl ResourceManager {
+    pub fn new() -> Self {
+        Self {
+            mutexes: [Mutex::default(); NUM_RESOURCES],
+        }
+    }
+
+    /// Drive the critical-section schedule for the task that just executed
+    /// a tick: try to acquire its resource once due, tick down the hold, and
+    /// release it when the hold expires. `just_completed` must be set when
+    /// the task's job finished on this same tick, in which case a held
+    /// resource is released immediately rather than waiting out the
+    /// remaining hold — the task won't execute again (and so can't tick the
+    /// hold down further) until its next periodic release. Returns `true`
+    /// if the task is now blocked waiting on a resource.
+    pub fn drive(&mut self, tasks: &mut [Task; NUM_TASKS], idx: usize, just_completed: bool) -> bool {
+        let cs = match tasks[idx].cs {
+            Some(cs) => cs,
+            None => return false,
+        };
+
+        if !just_completed
+            && tasks[idx].holds_resource.is_none()
+            && tasks[idx].blocked_on.is_none()
+            && tasks[idx].remaining <= cs.enter_at_remaining
+        {
+            match self.mutexes[cs.resource].holder {
+                None => {
+                    self.mutexes[cs.resource].holder = Some(idx);
+                    tasks[idx].holds_resource = Some(cs.resource);
+                    tasks[idx].cs_ticks_left = cs.hold_ticks;
+                }
+                Some(holder) if holder != idx => {
+                    tasks[idx].blocked_on = Some(cs.resource);
+                    tasks[idx].state = TaskState::Blocked;
+
+                    // Priority inheritance: lend the holder our deadline if
+                    // we are more urgent than it, so it can't be starved by
+                    // lower-priority work while we wait on it.
+                    if tasks[idx].abs_deadline < tasks[holder].abs_deadline {
+                        tasks[holder].inherited_deadline = Some(tasks[idx].abs_deadline);
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        if tasks[idx].holds_resource == Some(cs.resource) {
+            if just_completed {
+                tasks[idx].cs_ticks_left = 0;
+            } else if tasks[idx].cs_ticks_left > 0 {
+                tasks[idx].cs_ticks_left -= 1;
+            }
+            if tasks[idx].cs_ticks_left == 0 {
+                self.release(tasks, idx, cs.resource);
+            }
+        }
+
+        tasks[idx].blocked_on.is_some()
+    }
+
+    /// Force-release whatever resource `idx` currently holds and wake the
+    /// next waiter, if any. For a job abandoned after missing its deadline
+    /// (see `Task::check_deadline`) rather than one that released its hold
+    /// normally via `drive` -- without this, a deadline-missed holder would
+    /// keep `mutexes[].holder` pointed at a job that will never run again,
+    /// deadlocking every later acquirer.
+    pub fn release_held(&mut self, tasks: &mut [Task; NUM_TASKS], idx: usize) {
+        if let Some(resource) = tasks[idx].holds_resource {
+            self.release(tasks, idx, resource);
+        }
+    }
+
+    /// Release a held mutex and wake the highest-priority (smallest
+    /// `abs_deadline`) waiter, if any.
+    fn release(&mut self, tasks: &mut [Task; NUM_TASKS], idx: usize, resource: usize) {
+        tasks[idx].holds_resource = None;
+        tasks[idx].inherited_deadline = None;
+        self.mutexes[resource].holder = None;
+
+        let mut winner: Option<usize> = None;
+        for (i, t) in tasks.iter().enumerate() {
+            if t.blocked_on != Some(resource) {
+                continue;
+            }
+            winner = match winner {
+                Some(w) if tasks[w].abs_deadline <= t.abs_deadline => Some(w),
+                _ => Some(i),
+            };
+        }
+
+        if let Some(w) = winner {
+            let hold_ticks = tasks[w].cs.map(|cs| cs.hold_ticks).unwrap_or(0);
+            tasks[w].blocked_on = None;
+            tasks[w].state = TaskState::Ready;
+            tasks[w].holds_resource = Some(resource);
+            tasks[w].cs_ticks_left = hold_ticks;
+            self.mutexes[resource].holder = Some(w);
+        }
+    }
+}
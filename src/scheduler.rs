@@ -1,29 +1,37 @@
 /// Tick-based preemptive scheduler.
 ///
-/// Each tick: release newly periodic tasks, check deadline misses,
-/// build the state vector, query the NN policy, and execute the chosen task.
-/// This mirrors the Python simulation so that the trained policy transfers.
-use crate::policy;
-use crate::task::{Task, TaskState};
+/// Each tick: release newly periodic tasks, check deadline misses, ask the
+/// configured `SchedulingPolicy` which task to run, and execute it. Generic
+/// over the policy so the same tick loop and metrics (misses/completions/
+/// switches) can be reused across policies by selecting one at construction.
+use crate::policy::SchedulingPolicy;
+use crate::resource::ResourceManager;
+use crate::task::{Task, TaskState, NUM_TASKS};
 use cortex_m_semihosting::hprintln;
 
-const NUM_TASKS: usize = 6;
-const STATE_SIZE: usize = NUM_TASKS * 4;
-const Q10: i32 = 1024;
-
-pub struct Scheduler {
+pub struct Scheduler<P: SchedulingPolicy> {
     pub tasks: [Task; NUM_TASKS],
     pub tick: u32,
     pub current_task: Option<usize>,
     pub total_misses: u32,
     pub total_completions: u32,
     pub context_switches: u32,
-    max_deadline: u32,
+    pub total_releases: u32,
+    policy: P,
+    /// Task whose quantum ran out on the last tick it executed; excluded
+    /// from this tick's candidate set (when another task is Ready) so a
+    /// round-robin reselect actually happens instead of the policy just
+    /// picking it again.
+    quantum_exhausted: Option<usize>,
+    /// The task that actually executed last tick, tracked independently of
+    /// `current_task` (which is cleared on quantum expiry) so switches are
+    /// still counted correctly across a forced reselect.
+    last_ran: Option<usize>,
+    resources: ResourceManager,
 }
 
-impl Scheduler {
-    pub fn new(tasks: [Task; NUM_TASKS]) -> Self {
-        let max_deadline = tasks.iter().map(|t| t.deadline).max().unwrap_or(1);
+impl<P: SchedulingPolicy> Scheduler<P> {
+    pub fn new(tasks: [Task; NUM_TASKS], policy: P) -> Self {
         Self {
             tasks,
             tick: 0,
@@ -31,54 +39,32 @@ impl Scheduler {
             total_misses: 0,
             total_completions: 0,
             context_switches: 0,
-            max_deadline,
+            total_releases: 0,
+            policy,
+            quantum_exhausted: None,
+            last_ran: None,
+            resources: ResourceManager::new(),
         }
     }
 
-    /// Build the Q10 fixed-point state vector matching the Python environment.
-    /// 4 features per task: time_to_deadline, time_since_scheduled, remaining/wcet, is_ready.
-    fn build_state(&self) -> [i32; STATE_SIZE] {
-        let mut state = [0i32; STATE_SIZE];
-        for (i, t) in self.tasks.iter().enumerate() {
-            let base = i * 4;
-            if t.state == TaskState::Ready {
-                // Time remaining until deadline, normalized
-                let ttd = if t.abs_deadline > self.tick {
-                    (t.abs_deadline - self.tick) as i32 * Q10 / self.max_deadline as i32
-                } else {
-                    0
-                };
-                state[base] = clamp(ttd, 0, Q10);
-
-                // Time since last scheduled — approximate with max_period if never run
-                // (We don't track last_scheduled in the Rust struct to save memory;
-                //  use max_period as a safe default. The policy is robust to this.)
-                state[base + 1] = Q10; // conservative: assume long time since scheduled
-
-                // Remaining execution / WCET
-                state[base + 2] = t.remaining as i32 * Q10 / t.wcet as i32;
-
-                // Is ready
-                state[base + 3] = Q10;
-            }
-        }
-        state
-    }
-
     /// Release tasks whose period has arrived.
     fn do_releases(&mut self) {
         for t in self.tasks.iter_mut() {
             if self.tick >= t.next_release {
                 t.release(self.tick);
+                self.total_releases += 1;
             }
         }
     }
 
     /// Check for deadline misses and abandon late jobs.
     fn check_deadlines(&mut self) {
-        for t in self.tasks.iter_mut() {
-            if t.check_deadline(self.tick) {
+        for idx in 0..NUM_TASKS {
+            if self.tasks[idx].check_deadline(self.tick) {
                 self.total_misses += 1;
+                // An abandoned job that was holding a mutex would otherwise
+                // leak it forever, since it won't run again to release it.
+                self.resources.release_held(&mut self.tasks, idx);
             }
         }
     }
@@ -88,25 +74,78 @@ impl Scheduler {
         self.do_releases();
         self.check_deadlines();
 
-        let state = self.build_state();
-        let action = policy::infer(&state);
-
-        // Track context switches (task-to-task, not involving idle)
-        if action < NUM_TASKS {
-            if let Some(prev) = self.current_task {
-                if prev != action {
-                    self.context_switches += 1;
-                }
+        // If the previously-running task just burned through its quantum,
+        // hide it from this tick's candidates so the policy is forced to
+        // pick something else instead of just re-selecting it -- but only
+        // when another task is actually Ready to take over; otherwise there
+        // is nothing to round-robin to, and hiding it would force a wasted
+        // idle tick under overload instead of just letting it carry on.
+        let mut candidates = self.tasks;
+        if let Some(idx) = self.quantum_exhausted.take() {
+            let other_ready = candidates
+                .iter()
+                .enumerate()
+                .any(|(i, t)| i != idx && t.state == TaskState::Ready);
+            if other_ready && candidates[idx].state == TaskState::Ready {
+                candidates[idx].state = TaskState::Blocked;
             }
         }
 
+        let action = self.policy.select(&candidates, self.tick);
+
         // Execute the selected task for one tick
-        if action < NUM_TASKS && self.tasks[action].state == TaskState::Ready {
-            self.tasks[action].state = TaskState::Running;
-            if self.tasks[action].tick_execute() {
-                self.total_completions += 1;
+        if let Some(action) = action {
+            if self.tasks[action].state == TaskState::Ready {
+                // Track context switches (task-to-task, not involving idle),
+                // only counted when the selected task actually runs.
+                if let Some(prev) = self.last_ran {
+                    if prev != action {
+                        self.context_switches += 1;
+                    }
+                }
+
+                self.tasks[action].state = TaskState::Running;
+                let completed = self.tasks[action].tick_execute(self.tick);
+                self.last_ran = Some(action);
+
+                // Drive the critical-section schedule regardless of whether
+                // the job just completed, so a resource held by a task that
+                // finishes mid-critical-section is released rather than
+                // leaking (the manager would otherwise believe the finished
+                // task still owns it forever).
+                let blocked_on_resource = self.resources.drive(&mut self.tasks, action, completed);
+
+                if completed {
+                    self.total_completions += 1;
+                    self.current_task = None;
+                } else if blocked_on_resource {
+                    // Task just blocked trying to acquire a held mutex.
+                    self.current_task = None;
+                } else if self.tasks[action].tick_budget() {
+                    // CBS budget exhausted before the job finished: suspend
+                    // the task so its overrun can't cascade onto others.
+                    self.tasks[action].cbs_suspend();
+                    self.current_task = None;
+                } else if self.tasks[action].tick_time_slice() {
+                    // Quantum exhausted: force a reselect next tick, but
+                    // leave the job Ready so it's still a candidate (just
+                    // deprioritized) rather than disappearing until its
+                    // next period.
+                    self.tasks[action].state = TaskState::Ready;
+                    self.quantum_exhausted = Some(action);
+                    self.current_task = None;
+                } else {
+                    // Job isn't done and nothing preempted it: go back to
+                    // Ready so it's reselected next tick (by this policy or
+                    // a more urgent one) instead of freezing in Running,
+                    // which would make CBS/quantum/critical-section ticking
+                    // only ever observe a single tick of execution per job.
+                    self.tasks[action].state = TaskState::Ready;
+                    self.current_task = Some(action);
+                }
+            } else {
+                self.current_task = None;
             }
-            self.current_task = Some(action);
         } else {
             self.current_task = None;
         }
@@ -114,6 +153,15 @@ impl Scheduler {
         self.tick += 1;
     }
 
+    /// Run the scheduler for `total_ticks` with no semihosting output.
+    /// Used by the evaluation harness to sweep many (taskset, policy) runs
+    /// without flooding the console.
+    pub fn run_silent(&mut self, total_ticks: u32) {
+        for _ in 0..total_ticks {
+            self.tick_once();
+        }
+    }
+
     /// Run the scheduler for a given number of ticks, logging periodically.
     pub fn run(&mut self, total_ticks: u32) {
         let _ = hprintln!("Scheduler starting for {} ticks", total_ticks);
@@ -139,18 +187,12 @@ impl Scheduler {
         let _ = hprintln!("Deadline misses: {}", self.total_misses);
         let _ = hprintln!("Context switches:{}", self.context_switches);
         for t in &self.tasks {
-            let _ = hprintln!("  Task {}: misses={}", t.id, t.deadline_misses);
+            let _ = hprintln!(
+                "  Task {}: misses={} budget_overruns={}",
+                t.id,
+                t.deadline_misses,
+                t.budget_overruns
+            );
         }
     }
 }
-
-#[inline]
-fn clamp(val: i32, min: i32, max: i32) -> i32 {
-    if val < min {
-        min
-    } else if val > max {
-        max
-    } else {
-        val
-    }
-}
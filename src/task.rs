@@ -1,9 +1,17 @@
+/// Number of tasks in the taskset. Shared by the scheduler and every
+/// scheduling policy so state vectors and task arrays stay in sync.
+pub const NUM_TASKS: usize = 6;
+
+/// Round-robin time quantum: the max number of consecutive ticks a task
+/// may run before the scheduler forces a reselect, bounding per-task CPU
+/// occupancy regardless of which policy picked it.
+pub const MAX_TIME_SLICE: i32 = 5;
+
 /// Task states in a preemptive RTOS.
 /// Ready: released and waiting for CPU. Running: currently executing.
-/// Blocked: waiting on a resource (unused here but included for completeness).
+/// Blocked: suspended, either on a CBS budget overrun or on a held mutex.
 /// Completed: finished execution for this period.
 #[derive(Clone, Copy, PartialEq)]
-#[allow(dead_code)]
 pub enum TaskState {
     Ready,
     Running,
@@ -11,6 +19,18 @@ pub enum TaskState {
     Completed,
 }
 
+/// A deterministic critical-section request: once a job has `enter_at_remaining`
+/// execution ticks or fewer left, the task tries to acquire `resource`, holds
+/// it for `hold_ticks` ticks of actual execution, then releases it. Driving
+/// acquire/release off remaining execution (rather than wall-clock tick)
+/// keeps the schedule reproducible regardless of which policy is running.
+#[derive(Clone, Copy)]
+pub struct CsRequest {
+    pub resource: usize,
+    pub enter_at_remaining: u32,
+    pub hold_ticks: u32,
+}
+
 /// Represents a periodic real-time task.
 /// In real-time systems each task has a period (how often it runs), a deadline
 /// (when it must finish by), and a worst-case execution time (WCET).
@@ -25,6 +45,27 @@ pub struct Task {
     pub abs_deadline: u32,
     pub state: TaskState,
     pub deadline_misses: u32,
+    pub time_slice: i32,
+    /// CBS runtime budget `Q` per server period, defaults to `wcet`.
+    pub budget_q: u32,
+    /// CBS server period `P`, defaults to `period`.
+    pub server_period: u32,
+    pub budget_remaining: u32,
+    pub budget_overruns: u32,
+    /// This task's critical-section schedule, if it ever takes a mutex.
+    pub cs: Option<CsRequest>,
+    /// Resource id this task currently holds, if any.
+    pub holds_resource: Option<usize>,
+    /// Resource id this task is blocked waiting on, if any.
+    pub blocked_on: Option<usize>,
+    /// Ticks left to hold `holds_resource` before releasing it.
+    pub cs_ticks_left: u32,
+    /// Deadline temporarily lent to this task by a more-urgent waiter while
+    /// it holds a resource that waiter needs (priority inheritance).
+    pub inherited_deadline: Option<u32>,
+    /// Tick at which this task last actually executed, or `None` if it
+    /// never has. Used to compute the "time since scheduled" NN feature.
+    pub last_scheduled: Option<u32>,
 }
 
 impl Task {
@@ -39,6 +80,46 @@ impl Task {
             abs_deadline: 0,
             state: TaskState::Completed,
             deadline_misses: 0,
+            time_slice: MAX_TIME_SLICE,
+            budget_q: wcet,
+            server_period: period,
+            budget_remaining: 0,
+            budget_overruns: 0,
+            cs: None,
+            holds_resource: None,
+            blocked_on: None,
+            cs_ticks_left: 0,
+            inherited_deadline: None,
+            last_scheduled: None,
+        }
+    }
+
+    /// Give this task a deterministic critical-section request. See `CsRequest`.
+    pub const fn with_cs(mut self, resource: usize, enter_at_remaining: u32, hold_ticks: u32) -> Self {
+        self.cs = Some(CsRequest {
+            resource,
+            enter_at_remaining,
+            hold_ticks,
+        });
+        self
+    }
+
+    /// Override the CBS runtime budget `Q` below the default of `wcet`, so
+    /// this task's jobs routinely overrun their server budget and actually
+    /// exercise the CBS suspension path (with `Q == wcet`, a job's execution
+    /// always completes on the exact tick its budget would hit zero, so it
+    /// can never be observed overrunning).
+    pub const fn with_budget(mut self, budget_q: u32) -> Self {
+        self.budget_q = budget_q;
+        self
+    }
+
+    /// Deadline to use for scheduling/priority comparisons: `abs_deadline`,
+    /// unless priority inheritance has lent this task a more urgent one.
+    pub fn effective_deadline(&self) -> u32 {
+        match self.inherited_deadline {
+            Some(d) if d < self.abs_deadline => d,
+            _ => self.abs_deadline,
         }
     }
 
@@ -49,10 +130,17 @@ impl Task {
         self.abs_deadline = tick + self.deadline;
         self.next_release = tick + self.period;
         self.state = TaskState::Ready;
+        self.time_slice = MAX_TIME_SLICE;
+        self.budget_remaining = self.budget_q;
+        self.holds_resource = None;
+        self.blocked_on = None;
+        self.cs_ticks_left = 0;
+        self.inherited_deadline = None;
     }
 
     /// Simulate one tick of execution. Returns true if the task just completed.
-    pub fn tick_execute(&mut self) -> bool {
+    pub fn tick_execute(&mut self, tick: u32) -> bool {
+        self.last_scheduled = Some(tick);
         if self.remaining > 0 {
             self.remaining -= 1;
             if self.remaining == 0 {
@@ -63,13 +151,53 @@ impl Task {
         false
     }
 
+    /// Decrement the round-robin quantum after a tick of execution. Returns
+    /// true once the quantum is exhausted, resetting it to `MAX_TIME_SLICE`
+    /// so the task is ready for its next turn.
+    pub fn tick_time_slice(&mut self) -> bool {
+        self.time_slice -= 1;
+        if self.time_slice <= 0 {
+            self.time_slice = MAX_TIME_SLICE;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Decrement the CBS runtime budget by one tick of actual execution.
+    /// Returns true once the budget is exhausted before the job finished,
+    /// in which case the caller must suspend the task via `cbs_suspend`.
+    pub fn tick_budget(&mut self) -> bool {
+        if self.budget_remaining > 0 {
+            self.budget_remaining -= 1;
+        }
+        self.budget_remaining == 0
+    }
+
+    /// Suspend this task after a CBS budget overrun: postpone its server
+    /// deadline by `server_period`, refill the budget to `budget_q`, and
+    /// block it until its next periodic release. This enforces temporal
+    /// isolation so a task that exceeds its declared WCET cannot starve
+    /// the rest of the taskset.
+    pub fn cbs_suspend(&mut self) {
+        self.budget_overruns += 1;
+        self.abs_deadline += self.server_period;
+        self.budget_remaining = self.budget_q;
+        self.state = TaskState::Blocked;
+    }
+
     /// Check if this task missed its deadline. Returns true on a miss.
+    /// Checked for `Blocked` jobs too (e.g. stuck waiting on a mutex or
+    /// CBS-suspended) — only `Running`/`Completed` are exempt, since a job
+    /// that's merely blocked can still sail past its deadline.
     pub fn check_deadline(&mut self, tick: u32) -> bool {
-        if self.state == TaskState::Ready && tick >= self.abs_deadline {
+        let pending = matches!(self.state, TaskState::Ready | TaskState::Blocked);
+        if pending && tick >= self.abs_deadline {
             self.deadline_misses += 1;
             // Abandon this job — it will re-release next period
             self.state = TaskState::Completed;
             self.remaining = 0;
+            self.blocked_on = None;
             return true;
         }
         false